@@ -0,0 +1,14 @@
+use rand::Rng;
+use std::time::Duration;
+
+/// Computes a full-jitter exponential backoff delay for the given attempt number.
+///
+/// `delay = min(cap, base * 2^attempt)`, then a uniformly random duration in
+/// `[0, delay]` is returned so that many retries scheduled at the same time don't
+/// all wake up and hammer the backend at once.
+pub fn full_jitter(attempt: u32, base: Duration, cap: Duration) -> Duration {
+    let multiplier = 2u32.checked_pow(attempt).unwrap_or(u32::MAX);
+    let delay = base.checked_mul(multiplier).unwrap_or(cap).min(cap);
+
+    Duration::from_secs_f64(rand::thread_rng().gen_range(0.0..=delay.as_secs_f64()))
+}
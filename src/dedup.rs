@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::{env, time::Duration};
+
+const DEFAULT_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Serialize, Deserialize, Clone)]
+struct SeenEntry {
+    id: String,
+    seen_at: DateTime<Utc>,
+}
+
+/// Suppresses duplicate submissions. Sheet-fed scanners and flaky network
+/// retries commonly resend the identical page, which would otherwise
+/// produce duplicate documents in every backend. Content hashes are
+/// remembered for a configurable window and pruned lazily on lookup.
+#[derive(Clone)]
+pub struct Dedup {
+    db: sled::Db,
+    window: Duration,
+}
+
+impl Dedup {
+    pub fn open(path: impl AsRef<std::path::Path>, window: Duration) -> sled::Result<Self> {
+        Ok(Self {
+            db: sled::open(path)?,
+            window,
+        })
+    }
+
+    /// Builds a per-user dedup store from `{USER}_DEDUP*` env vars, or
+    /// `None` if the user has disabled it via `{USER}_DEDUP=false`.
+    pub fn from_env(user: &str) -> Option<Self> {
+        let u = user.to_uppercase();
+
+        let enabled = env::var(format!("{u}_DEDUP"))
+            .map(|value| !matches!(value.to_lowercase().as_str(), "0" | "false" | "off"))
+            .unwrap_or(true);
+
+        if !enabled {
+            return None;
+        }
+
+        let window = env::var(format!("{u}_DEDUP_WINDOW_SECS"))
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_WINDOW);
+
+        let path =
+            env::var(format!("{u}_DEDUP_PATH")).unwrap_or_else(|_| format!("dedup-{user}.sled"));
+
+        Some(Self::open(&path, window).expect("Failed to open dedup store"))
+    }
+
+    /// Returns `true` and suppresses the submission if this content was seen
+    /// within the window; otherwise records it under `id` and returns
+    /// `false`.
+    pub fn is_duplicate(&self, bytes: &[u8], id: &str) -> bool {
+        let now = Utc::now();
+        self.prune(now);
+
+        let hash = blake3::hash(bytes).to_hex().to_string();
+
+        if let Some(entry) = self
+            .db
+            .get(&hash)
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_slice::<SeenEntry>(&value).ok())
+        {
+            debug!("{id}\tSuppressing duplicate of {} (hash = {hash})", entry.id);
+            return true;
+        }
+
+        let entry = SeenEntry {
+            id: id.to_string(),
+            seen_at: now,
+        };
+
+        if let Ok(value) = serde_json::to_vec(&entry) {
+            let _ = self.db.insert(&hash, value);
+        }
+
+        false
+    }
+
+    fn prune(&self, now: DateTime<Utc>) {
+        let expired: Vec<sled::IVec> = self
+            .db
+            .iter()
+            .filter_map(Result::ok)
+            .filter_map(|(key, value)| {
+                serde_json::from_slice::<SeenEntry>(&value)
+                    .ok()
+                    .map(|entry| (key, entry))
+            })
+            .filter(|(_, entry)| {
+                now.signed_duration_since(entry.seen_at)
+                    .to_std()
+                    .map(|age| age > self.window)
+                    .unwrap_or(false)
+            })
+            .map(|(key, _)| key)
+            .collect();
+
+        for key in expired {
+            let _ = self.db.remove(key);
+        }
+    }
+}
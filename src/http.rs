@@ -1,5 +1,6 @@
-use crate::user::UserMap;
+use crate::{local::FileEntry, user::UserMap};
 use reqwest::StatusCode;
+use std::env;
 use warp::{
     reject::{Reject, Rejection},
     Filter,
@@ -30,10 +31,89 @@ pub async fn run(users: UserMap) {
             }
         });
 
+    let list = {
+        let users = users.clone();
+
+        warp::get()
+            .and(warp::path::end())
+            .and(authenticated())
+            .map(move || {
+                let files: Vec<FileEntry> = users
+                    .iter()
+                    .flat_map(|(name, user)| {
+                        user.local().into_iter().flat_map(move |local| {
+                            local
+                                .list()
+                                .into_iter()
+                                .map(move |(id, scanned_at)| FileEntry {
+                                    path: format!("/{name}/{id}.pdf"),
+                                    scanned_at,
+                                })
+                        })
+                    })
+                    .collect();
+
+                warp::reply::json(&files)
+            })
+    };
+
+    let download = {
+        let users = users.clone();
+
+        warp::get()
+            .and(warp::path!(String / String))
+            .and(authenticated())
+            .and_then(move |user: String, file: String| {
+                let users = users.clone();
+
+                async move {
+                    let id = file.strip_suffix(".pdf").unwrap_or(&file);
+                    let local = users.get(&user).and_then(|user| user.local().cloned());
+
+                    let bytes = match local {
+                        Some(local) => local.read(id).await,
+                        None => None,
+                    };
+
+                    match bytes {
+                        Some(bytes) => Ok(warp::reply::with_header(
+                            bytes,
+                            "Content-Type",
+                            "application/pdf",
+                        )),
+                        None => Err(warp::reject::not_found()),
+                    }
+                }
+            })
+    };
+
+    let delete = warp::delete()
+        .and(warp::path!(String / String))
+        .and(authenticated())
+        .and_then(move |user: String, file: String| {
+            let users = users.clone();
+
+            async move {
+                let id = file.strip_suffix(".pdf").unwrap_or(&file);
+
+                match users.get(&user).and_then(|user| user.local()) {
+                    Some(local) => {
+                        local.delete(id).await;
+                        Ok(StatusCode::GONE)
+                    }
+                    None => Ok(StatusCode::NOT_FOUND),
+                }
+            }
+        });
+
     let routes = head_root
         .or(head)
         .or(store)
+        .or(list)
+        .or(download)
+        .or(delete)
         .or(health_probe)
+        .recover(recover_unauthorized)
         .with(warp::log("scan2webdav::http"));
 
     let signal = async move {
@@ -63,3 +143,39 @@ pub fn internal_error(error: impl ToString) -> Rejection {
 }
 
 impl Reject for InternalError {}
+
+/// Gates the retrieval API behind `Authorization: Bearer {SCAN_TOKEN}`. If
+/// `SCAN_TOKEN` isn't configured the routes behind this filter are disabled.
+fn authenticated() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    let token = env::var("SCAN_TOKEN").ok();
+
+    warp::header::optional::<String>("authorization")
+        .and_then(move |header: Option<String>| {
+            let expected = token.clone().map(|token| format!("Bearer {token}"));
+
+            async move {
+                match (expected, header) {
+                    (Some(expected), Some(header)) if expected == header => Ok(()),
+                    _ => Err(warp::reject::custom(Unauthorized)),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+/// Rejected by [`authenticated`] on a missing/incorrect `Authorization`
+/// header.
+#[derive(Debug)]
+struct Unauthorized;
+
+impl Reject for Unauthorized {}
+
+/// Translates [`Unauthorized`] into a bare `401`; every other rejection falls
+/// through to warp's default handling.
+async fn recover_unauthorized(rejection: Rejection) -> Result<impl warp::Reply, Rejection> {
+    if rejection.find::<Unauthorized>().is_some() {
+        Ok(StatusCode::UNAUTHORIZED)
+    } else {
+        Err(rejection)
+    }
+}
@@ -0,0 +1,110 @@
+use crate::StorageBackend;
+use log::debug;
+use reqwest::Body;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::fs;
+
+/// A single row of the `GET /` listing: the route to fetch/delete the file
+/// and the scan id it was stored under.
+#[derive(Serialize)]
+pub struct FileEntry {
+    pub path: String,
+    pub scanned_at: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct IndexedFile {
+    id: String,
+    scanned_at: String,
+    size: u64,
+}
+
+/// Stores PDFs on disk and indexes them in sled so they can be listed,
+/// downloaded and deleted later through the retrieval REST API.
+#[derive(Clone)]
+pub struct LocalStorage {
+    dir: PathBuf,
+    index: sled::Db,
+}
+
+impl LocalStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+
+        std::fs::create_dir_all(&dir)
+            .unwrap_or_else(|err| panic!("Failed to create local storage dir {dir:?}: {err}"));
+
+        let index = sled::open(dir.join("index.sled")).expect("Failed to open local storage index");
+
+        Self { dir, index }
+    }
+
+    fn file_name(id: &str) -> String {
+        format!("{id}.pdf")
+    }
+
+    /// Refuses anything but a bare scan id (alphanumeric and `-`), so a
+    /// client-supplied id can never escape `self.dir` via `/` or `..`.
+    fn path_for(&self, id: &str) -> Option<PathBuf> {
+        let safe = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+
+        safe.then(|| self.dir.join(Self::file_name(id)))
+    }
+
+    /// Lists all indexed files, most recently scanned first.
+    pub fn list(&self) -> Vec<(String, String)> {
+        let mut entries: Vec<IndexedFile> = self
+            .index
+            .iter()
+            .values()
+            .filter_map(Result::ok)
+            .filter_map(|value| serde_json::from_slice(&value).ok())
+            .collect();
+
+        entries.sort_by(|a, b| b.scanned_at.cmp(&a.scanned_at));
+        entries.into_iter().map(|e| (e.id, e.scanned_at)).collect()
+    }
+
+    /// Reads back a previously stored file's bytes, if present.
+    pub async fn read(&self, id: &str) -> Option<Vec<u8>> {
+        let path = self.path_for(id)?;
+        fs::read(path).await.ok()
+    }
+
+    /// Removes both the file and its index entry, if present.
+    pub async fn delete(&self, id: &str) {
+        let Some(path) = self.path_for(id) else {
+            return;
+        };
+
+        let _ = fs::remove_file(path).await;
+        let _ = self.index.remove(id);
+    }
+}
+
+impl StorageBackend for LocalStorage {
+    async fn put(&self, id: &str, body: Body) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let path = self.path_for(id).ok_or("Refusing to store unsafe scan id")?;
+
+        let bytes = body
+            .as_bytes()
+            .ok_or("Local storage requires a buffered body")?
+            .to_vec();
+
+        let size = bytes.len() as u64;
+        fs::write(path, &bytes).await?;
+
+        let entry = IndexedFile {
+            id: id.to_string(),
+            scanned_at: id.to_string(),
+            size,
+        };
+
+        self.index.insert(id, serde_json::to_vec(&entry)?)?;
+
+        debug!("{id}\tStored locally ({size} bytes)");
+
+        Ok(())
+    }
+}
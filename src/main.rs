@@ -1,8 +1,14 @@
 use reqwest::Body;
 use user::UserMap;
 
+mod backoff;
+mod dedup;
 mod http;
+mod local;
 mod paperless;
+mod sftp;
+mod smtp;
+mod spool;
 mod telegram;
 mod user;
 mod webdav;
@@ -13,7 +19,7 @@ async fn main() {
 
     let users = UserMap::from_env();
 
-    http::run(users).await;
+    tokio::join!(http::run(users.clone()), sftp::run(users));
 }
 
 #[trait_variant::make(StorageBackend: Send)]
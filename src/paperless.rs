@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use log::{debug, error};
 use reqwest::{
     multipart::{self, Part},
@@ -7,12 +8,13 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::{
     borrow::Cow,
+    path::Path,
     time::{Duration, Instant},
 };
 use tokio::{task::JoinSet, time::sleep};
 use uuid::Uuid;
 
-use crate::StorageBackend;
+use crate::{backoff, StorageBackend};
 
 type CowStr = Cow<'static, str>;
 
@@ -23,7 +25,57 @@ pub struct PaperlessClient {
 
     token: CowStr,
 
-    custom_fields: CustomFieldsPatch,
+    title_template: CowStr,
+    correspondent: Option<u64>,
+    document_type: Option<u64>,
+    tags: Vec<u64>,
+    custom_fields: Vec<CustomField>,
+    next_asn: Option<AsnCounter>,
+}
+
+/// Durably tracks the next Paperless archive serial number to assign, so a
+/// restart can't reissue one that's already been patched onto a document.
+/// Seeded once from `{USER}_PAPERLESS_ASN_START`; every call afterwards
+/// ignores that seed and continues from the persisted value.
+#[derive(Clone)]
+struct AsnCounter {
+    db: sled::Db,
+}
+
+impl AsnCounter {
+    fn open(path: impl AsRef<Path>, start: u64) -> sled::Result<Self> {
+        let db = sled::open(path)?;
+
+        if db.get("next")?.is_none() {
+            db.insert("next", start.to_be_bytes().to_vec())?;
+        }
+
+        Ok(Self { db })
+    }
+
+    /// Returns the next ASN to assign, durably advancing the counter first so
+    /// a crash between issuing and patching can never hand out the same ASN
+    /// twice.
+    async fn next(&self) -> u64 {
+        let mut issued = 0;
+
+        self.db
+            .fetch_and_update("next", |old| {
+                issued = old
+                    .map(|bytes| u64::from_be_bytes(bytes.try_into().expect("Corrupt ASN counter")))
+                    .unwrap_or_default();
+
+                Some((issued + 1).to_be_bytes().to_vec())
+            })
+            .expect("Failed to persist ASN counter");
+
+        self.db
+            .flush_async()
+            .await
+            .expect("Failed to flush ASN counter");
+
+        issued
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -32,16 +84,38 @@ pub struct CustomField {
     value: Value,
 }
 
-#[derive(Serialize, Clone)]
-struct CustomFieldsPatch {
+/// Mirrors the subset of Paperless's document PATCH body this client fills
+/// in after upload: only what `post_document` can't already set at consume
+/// time. `correspondent`/`document_type`/`tags` go on the upload form
+/// instead, so patching them again here would be a redundant, pointless
+/// request. Fields left unset are omitted entirely rather than sent as
+/// `null`.
+#[derive(Serialize, Clone, Default)]
+struct DocumentPatch {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     custom_fields: Vec<CustomField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archive_serial_number: Option<u64>,
+}
+
+impl DocumentPatch {
+    fn is_empty(&self) -> bool {
+        self.custom_fields.is_empty() && self.archive_serial_number.is_none()
+    }
 }
 
 impl PaperlessClient {
+    #[allow(clippy::too_many_arguments)]
     pub fn new<U, S>(
         endpoint: U,
         token: S,
         custom_fields: Vec<CustomField>,
+        title_template: impl Into<CowStr>,
+        correspondent: Option<u64>,
+        document_type: Option<u64>,
+        tags: Vec<u64>,
+        asn_start: Option<u64>,
+        asn_path: impl AsRef<Path>,
     ) -> reqwest::Result<Self>
     where
         U: IntoUrl,
@@ -51,22 +125,60 @@ impl PaperlessClient {
             http_client: Client::new(),
             endpoint: endpoint.into_url()?,
             token: token.into(),
-            custom_fields: CustomFieldsPatch { custom_fields },
+            title_template: title_template.into(),
+            correspondent,
+            document_type,
+            tags,
+            custom_fields,
+            next_asn: asn_start.map(|start| {
+                AsnCounter::open(asn_path, start).expect("Failed to open Paperless ASN counter")
+            }),
         })
     }
 
+    /// Expands the `{id}`/`{date}` placeholders in the configured title
+    /// template; both resolve to the scan id since it already is a
+    /// timestamp.
+    fn render_title(&self, id: &str) -> String {
+        self.title_template.replace("{id}", id).replace("{date}", id)
+    }
+
+    /// Recovers the original scan time from a scan id (built by `User::store`
+    /// as `Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true).replace(":",
+    /// "-")`), so a job that's retried hours later by the spool still uploads
+    /// with the time the page was actually scanned rather than the time of
+    /// the retry.
+    fn scan_time(id: &str) -> DateTime<Utc> {
+        id.split_once('T')
+            .and_then(|(date, time)| {
+                DateTime::parse_from_rfc3339(&format!("{date}T{}", time.replace('-', ":"))).ok()
+            })
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(Utc::now)
+    }
+
     async fn set_document_attributes(
         &self,
         document_id: DocumentID,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if self.custom_fields.custom_fields.is_empty() {
+        let archive_serial_number = match &self.next_asn {
+            Some(counter) => Some(counter.next().await),
+            None => None,
+        };
+
+        let patch = DocumentPatch {
+            custom_fields: self.custom_fields.clone(),
+            archive_serial_number,
+        };
+
+        if patch.is_empty() {
             return Ok(());
         }
 
         self.http_client
             .patch(self.url(&["api", "documents", &document_id, ""]))
             .header("Authorization", format!("Token {}", self.token))
-            .json(&self.custom_fields)
+            .json(&patch)
             .send()
             .await?
             .error_for_status()?;
@@ -134,6 +246,7 @@ impl PaperlessClient {
         timeout: Duration,
     ) -> Result<TaskResult, Box<dyn std::error::Error + Send + Sync>> {
         let start = Instant::now();
+        let mut attempt = 0;
 
         debug!("{id:?} Waiting for task");
 
@@ -145,8 +258,8 @@ impl PaperlessClient {
                 return Ok(result);
             }
 
-            // TODO Use exponential backoff
-            sleep(interval).await;
+            sleep(backoff::full_jitter(attempt, interval, timeout)).await;
+            attempt += 1;
         }
 
         Err("Timeout while waiting for processing.".into())
@@ -167,6 +280,21 @@ impl PaperlessClient {
         }))
     }
 
+    /// Looks up the task for an exact upload file name (as opposed to
+    /// [`Self::find_related_tasks`]'s prefix match, which also matches a
+    /// split upload's child tasks and is therefore too broad to pick a single
+    /// task to resume).
+    async fn find_task_by_file_name(
+        &self,
+        file_name: &str,
+    ) -> Result<Option<Task>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self
+            .fetch_tasks()
+            .await?
+            .into_iter()
+            .find(|task| task.file_name.as_deref() == Some(file_name)))
+    }
+
     async fn fetch_task(
         &self,
         id: &TaskID,
@@ -221,30 +349,68 @@ impl StorageBackend for PaperlessClient {
         id: &str,
         body: Body,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let url = self.url(&["api", "documents", "post_document", ""]);
-
         let file_name_prefix = format!("EpicPrinter-{id}");
+        let file_name = format!("{file_name_prefix}.pdf");
+
+        // `put` is retried from scratch by the spool on failure, so before
+        // uploading a fresh copy check whether the original upload task for
+        // this id already exists upstream (e.g. the previous attempt's
+        // `post_document` succeeded but `wait_for_processing` timed out
+        // afterwards). Matched on the exact file name rather than
+        // `find_related_tasks`'s prefix match, since a prior split upload's
+        // child tasks also share that prefix — resuming one of those here
+        // instead of the original task would skip `wait_for_processing`'s
+        // split handling and leave the other children unpatched.
+        let existing_task = self.find_task_by_file_name(&file_name).await?;
+
+        let upload_id = match existing_task {
+            Some(task) => {
+                debug!(
+                    "{id}\tResuming existing Paperless upload (task = {})",
+                    task.uuid
+                );
+                task.uuid
+            }
+            None => {
+                let url = self.url(&["api", "documents", "post_document", ""]);
 
-        let file = Part::stream(body)
-            .file_name(format!("{file_name_prefix}.pdf"))
-            .mime_str("application/pdf")?;
+                let file = Part::stream(body)
+                    .file_name(file_name)
+                    .mime_str("application/pdf")?;
 
-        let form = multipart::Form::new()
-            .text("title", id.to_string())
-            .part("document", file);
+                let mut form = multipart::Form::new()
+                    .text("title", self.render_title(id))
+                    .text("created", Self::scan_time(id).to_rfc3339())
+                    .part("document", file);
 
-        let upload_id = self
-            .http_client
-            .post(url)
-            .header("Authorization", format!("Token {}", self.token))
-            .multipart(form)
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<Uuid>()
-            .await?;
+                if let Some(correspondent) = self.correspondent {
+                    form = form.text("correspondent", correspondent.to_string());
+                }
+
+                if let Some(document_type) = self.document_type {
+                    form = form.text("document_type", document_type.to_string());
+                }
 
-        debug!("{id}\tUpload complete (task = {upload_id})");
+                for tag in &self.tags {
+                    form = form.text("tags", tag.to_string());
+                }
+
+                let upload_id = self
+                    .http_client
+                    .post(url)
+                    .header("Authorization", format!("Token {}", self.token))
+                    .multipart(form)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<Uuid>()
+                    .await?;
+
+                debug!("{id}\tUpload complete (task = {upload_id})");
+
+                upload_id
+            }
+        };
 
         let result = self
             .wait_for_processing(&upload_id, &file_name_prefix)
@@ -0,0 +1,278 @@
+//! An embedded SFTP server for scanners/MFPs that can only "Scan to SFTP".
+//!
+//! The SSH username is lowercased and looked up in [`UserMap`], and a
+//! successfully closed `*.pdf` upload
+//! is fed into the exact same [`User::store`] pipeline used by the WebDAV
+//! route, so every backend/notification configured for that user still
+//! fires. Virtual directories are flat: writing any `*.pdf` anywhere in the
+//! tree triggers ingest.
+
+use crate::user::UserMap;
+use async_trait::async_trait;
+use log::{debug, error, info, warn};
+use russh::{
+    server::{Auth, Config, Handler, Msg, Server as _, Session},
+    Channel, ChannelId,
+};
+use russh_keys::key::{KeyPair, PublicKey};
+use russh_sftp::protocol::{File, FileAttributes, Handle, Name, OpenFlags, Status, StatusCode, Version};
+use std::{collections::HashMap, env, net::SocketAddr, sync::Arc};
+use warp::hyper::body::Bytes;
+
+/// Caps how large a single SFTP upload may grow in memory. Scans are single
+/// PDFs from an MFP, never anywhere near this size; the limit exists so a
+/// client can't make `write` allocate an unbounded (or overflowing) buffer by
+/// writing at a huge offset.
+const MAX_UPLOAD_SIZE: usize = 256 * 1024 * 1024;
+
+pub async fn run(users: UserMap) {
+    let port: u16 = env::var("SFTP_PORT")
+        .ok()
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(2222);
+
+    let config = Arc::new(Config {
+        keys: vec![host_key()],
+        ..Default::default()
+    });
+
+    info!("Listening for SFTP on 0.0.0.0:{port}");
+
+    russh::server::run(config, ("0.0.0.0", port), SftpServer { users })
+        .await
+        .expect("SFTP server crashed");
+}
+
+/// Loads the host key from `SFTP_HOST_KEY_PATH` (PEM, OpenSSH format) or, if
+/// unset, generates an ephemeral one for the lifetime of the process.
+fn host_key() -> KeyPair {
+    match env::var("SFTP_HOST_KEY_PATH") {
+        Ok(path) => {
+            let pem = std::fs::read_to_string(&path)
+                .unwrap_or_else(|err| panic!("Failed to read SFTP host key at {path}: {err}"));
+
+            russh_keys::decode_secret_key(&pem, None).expect("Invalid SFTP host key")
+        }
+        Err(_) => {
+            warn!("SFTP_HOST_KEY_PATH not set, generating an ephemeral host key");
+            KeyPair::generate_ed25519().expect("Failed to generate SFTP host key")
+        }
+    }
+}
+
+#[derive(Clone)]
+struct SftpServer {
+    users: UserMap,
+}
+
+impl russh::server::Server for SftpServer {
+    type Handler = SshSession;
+
+    fn new_client(&mut self, _: Option<SocketAddr>) -> Self::Handler {
+        SshSession {
+            users: self.users.clone(),
+            user: None,
+        }
+    }
+}
+
+struct SshSession {
+    users: UserMap,
+    user: Option<String>,
+}
+
+impl SshSession {
+    /// Checks a candidate username/secret pair against `{USER}_SFTP_PASS`
+    /// and returns the lowercased user name on a match.
+    fn authenticate_password(&self, user: &str, password: &str) -> Option<String> {
+        let name = user.to_lowercase();
+        let expected = env::var(format!("{}_SFTP_PASS", name.to_uppercase())).ok()?;
+
+        (expected == password && self.users.get(&name).is_some()).then_some(name)
+    }
+
+    /// Checks a candidate username/key pair against `{USER}_SFTP_PUBKEY`
+    /// (an `authorized_keys`-style public key line) and returns the
+    /// lowercased user name on a match.
+    fn authenticate_public_key(&self, user: &str, key: &PublicKey) -> Option<String> {
+        let name = user.to_lowercase();
+        let expected = env::var(format!("{}_SFTP_PUBKEY", name.to_uppercase())).ok()?;
+        let expected = russh_keys::parse_public_key_base64(expected.split_whitespace().nth(1)?).ok()?;
+
+        (&expected == key && self.users.get(&name).is_some()).then_some(name)
+    }
+}
+
+#[async_trait]
+impl Handler for SshSession {
+    type Error = russh::Error;
+
+    async fn auth_password(mut self, user: &str, password: &str) -> Result<(Self, Auth), Self::Error> {
+        match self.authenticate_password(user, password) {
+            Some(name) => {
+                self.user = Some(name);
+                Ok((self, Auth::Accept))
+            }
+            None => Ok((
+                self,
+                Auth::Reject {
+                    proceed_with_methods: None,
+                },
+            )),
+        }
+    }
+
+    async fn auth_publickey(mut self, user: &str, key: &PublicKey) -> Result<(Self, Auth), Self::Error> {
+        match self.authenticate_public_key(user, key) {
+            Some(name) => {
+                self.user = Some(name);
+                Ok((self, Auth::Accept))
+            }
+            None => Ok((
+                self,
+                Auth::Reject {
+                    proceed_with_methods: None,
+                },
+            )),
+        }
+    }
+
+    async fn channel_open_session(self, _channel: Channel<Msg>, _session: Session) -> Result<(Self, bool), Self::Error> {
+        Ok((self, true))
+    }
+
+    async fn subsystem_request(self, channel_id: ChannelId, name: &str, mut session: Session) -> Result<(Self, Session), Self::Error> {
+        if name != "sftp" {
+            session.channel_failure(channel_id);
+            return Ok((self, session));
+        }
+
+        let user = self
+            .user
+            .clone()
+            .expect("SFTP subsystem requested on an unauthenticated session");
+
+        session.channel_success(channel_id);
+        russh_sftp::server::run(channel_id, session.handle(), ScanSftpHandler::new(user, self.users.clone())).await;
+
+        Ok((self, session))
+    }
+}
+
+/// Bridges SFTP file writes onto [`User::store`]. Files are buffered fully
+/// in memory before being dispatched on close, mirroring how the HTTP PUT
+/// route receives the whole body before calling `store`.
+struct ScanSftpHandler {
+    user: String,
+    users: UserMap,
+    open_files: HashMap<String, PendingFile>,
+    next_handle: u64,
+}
+
+struct PendingFile {
+    name: String,
+    data: Vec<u8>,
+}
+
+impl ScanSftpHandler {
+    fn new(user: String, users: UserMap) -> Self {
+        Self {
+            user,
+            users,
+            open_files: HashMap::new(),
+            next_handle: 0,
+        }
+    }
+
+    fn allocate_handle(&mut self) -> String {
+        let handle = self.next_handle.to_string();
+        self.next_handle += 1;
+        handle
+    }
+
+    fn ok(id: u32) -> Status {
+        Status {
+            id,
+            status_code: StatusCode::Ok,
+            error_message: "Ok".into(),
+            language_tag: "en-US".into(),
+        }
+    }
+}
+
+#[async_trait]
+impl russh_sftp::server::Handler for ScanSftpHandler {
+    type Error = StatusCode;
+
+    fn unimplemented(&self) -> Self::Error {
+        StatusCode::OpUnsupported
+    }
+
+    async fn init(&mut self, _version: u32, _extensions: HashMap<String, String>) -> Result<Version, Self::Error> {
+        Ok(Version::new())
+    }
+
+    async fn open(&mut self, id: u32, filename: String, _pflags: OpenFlags, _attrs: FileAttributes) -> Result<Handle, Self::Error> {
+        let handle = self.allocate_handle();
+
+        self.open_files.insert(
+            handle.clone(),
+            PendingFile {
+                name: filename,
+                data: Vec::new(),
+            },
+        );
+
+        Ok(Handle { id, handle })
+    }
+
+    async fn write(&mut self, id: u32, handle: String, offset: u64, data: Vec<u8>) -> Result<Status, Self::Error> {
+        let file = self.open_files.get_mut(&handle).ok_or(StatusCode::Failure)?;
+
+        let offset = usize::try_from(offset).map_err(|_| StatusCode::Failure)?;
+        let end = offset.checked_add(data.len()).ok_or(StatusCode::Failure)?;
+
+        if end > MAX_UPLOAD_SIZE {
+            warn!("Rejecting SFTP write past the {MAX_UPLOAD_SIZE} byte upload limit (handle = {handle})");
+            return Err(StatusCode::Failure);
+        }
+
+        if file.data.len() < end {
+            file.data.resize(end, 0);
+        }
+        file.data[offset..end].copy_from_slice(&data);
+
+        Ok(Self::ok(id))
+    }
+
+    async fn close(&mut self, id: u32, handle: String) -> Result<Status, Self::Error> {
+        let Some(file) = self.open_files.remove(&handle) else {
+            return Ok(Self::ok(id));
+        };
+
+        if !file.name.to_lowercase().ends_with(".pdf") {
+            debug!("Ignoring non-PDF SFTP upload: {}", file.name);
+            return Ok(Self::ok(id));
+        }
+
+        let Some(user) = self.users.get(&self.user) else {
+            error!("SFTP upload from unknown user '{}', dropping {}", self.user, file.name);
+            return Ok(Self::ok(id));
+        };
+
+        debug!("{}\tSFTP upload closed ({} bytes)", file.name, file.data.len());
+
+        if let Err(rejection) = user.store(Bytes::from(file.data)).await {
+            error!("{}\tFailed to ingest SFTP upload: {rejection:?}", file.name);
+        }
+
+        Ok(Self::ok(id))
+    }
+
+    async fn realpath(&mut self, id: u32, path: String) -> Result<Name, Self::Error> {
+        Ok(Name {
+            id,
+            file: vec![File::dummy(&path)],
+        })
+    }
+}
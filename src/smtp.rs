@@ -0,0 +1,115 @@
+use crate::StorageBackend;
+use lettre::{
+    message::{header::ContentType, Attachment, MultiPart, SinglePart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use reqwest::Body;
+use std::{borrow::Cow, str::FromStr};
+
+type CowStr = Cow<'static, str>;
+
+/// How the connection to the SMTP relay is secured.
+#[derive(Clone, Copy)]
+pub enum Encryption {
+    /// Implicit TLS, i.e. "SMTPS" (typically port 465).
+    Tls,
+    /// Plaintext connection upgraded via `STARTTLS` (typically port 587).
+    StartTls,
+    /// No encryption at all, for local/trusted relays only.
+    None,
+}
+
+impl FromStr for Encryption {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "tls" | "implicit" => Ok(Self::Tls),
+            "starttls" => Ok(Self::StartTls),
+            "none" | "plain" => Ok(Self::None),
+            other => Err(format!("Unknown SMTP encryption mode: {other}")),
+        }
+    }
+}
+
+/// "Scan to email": sends the scanned PDF as an attachment through a
+/// configured SMTP relay.
+#[derive(Clone)]
+pub struct SmtpClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: CowStr,
+    to: CowStr,
+    subject_template: CowStr,
+}
+
+impl SmtpClient {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        host: impl AsRef<str>,
+        port: u16,
+        encryption: Encryption,
+        user: impl Into<CowStr>,
+        pass: impl Into<CowStr>,
+        from: impl Into<CowStr>,
+        to: impl Into<CowStr>,
+        subject_template: impl Into<CowStr>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let credentials = Credentials::new(user.into().into_owned(), pass.into().into_owned());
+
+        let builder = match encryption {
+            Encryption::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host.as_ref())?,
+            Encryption::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host.as_ref())?
+            }
+            Encryption::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host.as_ref())
+            }
+        };
+
+        let transport = builder.port(port).credentials(credentials).build();
+
+        Ok(Self {
+            transport,
+            from: from.into(),
+            to: to.into(),
+            subject_template: subject_template.into(),
+        })
+    }
+
+    /// Expands `{id}`/`{date}` placeholders; both resolve to the same scan id
+    /// since it is already a timestamp.
+    fn render(template: &str, id: &str) -> String {
+        template.replace("{id}", id).replace("{date}", id)
+    }
+}
+
+impl StorageBackend for SmtpClient {
+    async fn put(
+        &self,
+        id: &str,
+        body: Body,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let bytes = body
+            .as_bytes()
+            .ok_or("SMTP storage requires a buffered body")?
+            .to_vec();
+
+        let attachment = Attachment::new(format!("{id}.pdf"))
+            .body(bytes, ContentType::parse("application/pdf")?);
+
+        let email = Message::builder()
+            .from(self.from.parse()?)
+            .to(Self::render(&self.to, id).parse()?)
+            .subject(Self::render(&self.subject_template, id))
+            .multipart(
+                MultiPart::mixed()
+                    .singlepart(SinglePart::plain(format!("New scan attached: {id}")))
+                    .singlepart(attachment),
+            )?;
+
+        self.transport.send(email).await?;
+
+        Ok(())
+    }
+}
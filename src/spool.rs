@@ -0,0 +1,178 @@
+use crate::{backoff, user::UserMap};
+use chrono::{DateTime, Utc};
+use log::{debug, error, warn};
+use reqwest::Body;
+use serde::{Deserialize, Serialize};
+use std::{env, sync::Arc, time::Duration};
+use tokio::{sync::Notify, time::sleep};
+
+const BASE_DELAY: Duration = Duration::from_secs(5);
+const MAX_DELAY: Duration = Duration::from_secs(60 * 60);
+const MAX_ATTEMPTS: u32 = 10;
+const SWEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Which backend a spooled job is destined for.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackendKind {
+    Webdav,
+    Paperless,
+    Local,
+    Smtp,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Job {
+    user: String,
+    id: String,
+    backend: BackendKind,
+    bytes: Vec<u8>,
+    attempt: u32,
+    ready_at: DateTime<Utc>,
+}
+
+/// A sled-backed durable queue of pending uploads.
+///
+/// Every accepted PDF is written here before the upload request is
+/// acknowledged, and removed once the matching backend has confirmed
+/// receipt, or after `MAX_ATTEMPTS` failed deliveries (the user is then
+/// notified over Telegram). A background worker sweeps for due entries and
+/// delivers them, retrying failures with exponential backoff, so the server
+/// survives backend outages and process restarts.
+#[derive(Clone)]
+pub struct Spool {
+    db: sled::Db,
+    notify: Arc<Notify>,
+}
+
+impl Spool {
+    pub fn open(path: impl AsRef<std::path::Path>) -> sled::Result<Self> {
+        Ok(Self {
+            db: sled::open(path)?,
+            notify: Arc::new(Notify::new()),
+        })
+    }
+
+    pub fn from_env() -> Self {
+        let path = env::var("SPOOL_PATH").unwrap_or_else(|_| "spool.sled".into());
+
+        Self::open(&path).unwrap_or_else(|err| panic!("Failed to open spool at {path}: {err}"))
+    }
+
+    /// Durably persists a job. Flushes to disk before returning so that a
+    /// crash right after the caller is told `200 OK` can't lose the job —
+    /// sled otherwise only fsyncs on its background timer.
+    pub async fn enqueue(&self, user: &str, id: &str, backend: BackendKind, bytes: &[u8]) {
+        let job = Job {
+            user: user.to_string(),
+            id: id.to_string(),
+            backend,
+            bytes: bytes.to_vec(),
+            attempt: 0,
+            ready_at: Utc::now(),
+        };
+
+        let key = Self::key(user, id, backend);
+        let value = serde_json::to_vec(&job).expect("Failed to serialize spool job");
+
+        self.db.insert(key, value).expect("Failed to write to spool");
+        self.db.flush_async().await.expect("Failed to flush spool");
+        self.notify.notify_one();
+    }
+
+    fn key(user: &str, id: &str, backend: BackendKind) -> String {
+        format!("{user}/{id}/{backend:?}")
+    }
+
+    /// Spawns the background worker that drains due entries, once on startup
+    /// and again on every new submission.
+    pub fn spawn_worker(self, users: UserMap) {
+        tokio::spawn(async move {
+            loop {
+                self.sweep(&users).await;
+
+                tokio::select! {
+                    _ = sleep(SWEEP_INTERVAL) => {}
+                    _ = self.notify.notified() => {}
+                }
+            }
+        });
+    }
+
+    async fn sweep(&self, users: &UserMap) {
+        let now = Utc::now();
+
+        let due: Vec<(sled::IVec, Job)> = self
+            .db
+            .iter()
+            .filter_map(Result::ok)
+            .filter_map(|(key, value)| {
+                serde_json::from_slice::<Job>(&value)
+                    .ok()
+                    .map(|job| (key, job))
+            })
+            .filter(|(_, job)| job.ready_at <= now)
+            .collect();
+
+        for (key, job) in due {
+            self.attempt(users, key, job).await;
+        }
+    }
+
+    async fn attempt(&self, users: &UserMap, key: sled::IVec, mut job: Job) {
+        let Some(user) = users.get(&job.user) else {
+            warn!(
+                "{}\tSpool entry references unknown user '{}', dropping",
+                job.id, job.user
+            );
+            let _ = self.db.remove(&key);
+            return;
+        };
+
+        debug!(
+            "{}\tRetrying spool entry (backend = {:?}, attempt = {})",
+            job.id, job.backend, job.attempt
+        );
+
+        let result = user
+            .dispatch(job.backend, &job.id, Body::from(job.bytes.clone()))
+            .await;
+
+        match result {
+            Ok(_) => {
+                debug!(
+                    "{}\tSpool entry delivered (backend = {:?})",
+                    job.id, job.backend
+                );
+                let _ = self.db.remove(&key);
+            }
+
+            Err(err) if job.attempt + 1 >= MAX_ATTEMPTS => {
+                error!(
+                    "{}\tGiving up after {} attempts (backend = {:?}): {err:?}",
+                    job.id,
+                    job.attempt + 1,
+                    job.backend
+                );
+                user.notify_failure(&job.id, err.as_ref());
+                let _ = self.db.remove(&key);
+            }
+
+            Err(err) => {
+                job.attempt += 1;
+
+                let delay = backoff::full_jitter(job.attempt, BASE_DELAY, MAX_DELAY);
+                job.ready_at = Utc::now()
+                    + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::zero());
+
+                debug!(
+                    "{}\tScheduling retry {} (backend = {:?}) at {}: {err:?}",
+                    job.id, job.attempt, job.backend, job.ready_at
+                );
+
+                if let Ok(value) = serde_json::to_vec(&job) {
+                    let _ = self.db.insert(&key, value);
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,13 @@
 use crate::{
-    http::internal_error, paperless::PaperlessClient, telegram::TelegramClient,
-    webdav::WebdavClient, StorageBackend,
+    dedup::Dedup,
+    http::internal_error,
+    local::LocalStorage,
+    paperless::PaperlessClient,
+    smtp::{Encryption, SmtpClient},
+    spool::{BackendKind, Spool},
+    telegram::TelegramClient,
+    webdav::WebdavClient,
+    StorageBackend,
 };
 use chrono::{SecondsFormat, Utc};
 use log::{debug, error};
@@ -13,15 +20,20 @@ pub struct UserMap(Arc<HashMap<String, User>>);
 
 impl UserMap {
     pub fn from_env() -> Self {
+        let spool = Spool::from_env();
+
         let users = env::var("SCAN_USERS")
             .expect("No users provided")
             .split(",")
             .map(str::trim)
             .map(str::to_lowercase)
-            .map(|name| (name.clone(), User::from_env(name)))
+            .map(|name| (name.clone(), User::from_env(name, spool.clone())))
             .collect();
 
-        Self(Arc::new(users))
+        let map = Self(Arc::new(users));
+        spool.spawn_worker(map.clone());
+
+        map
     }
 }
 
@@ -39,24 +51,37 @@ pub struct User {
     webdav: Option<WebdavClient>,
     paperless: Option<PaperlessClient>,
     telegram: Option<TelegramClient>,
+    local: Option<LocalStorage>,
+    smtp: Option<SmtpClient>,
+    dedup: Option<Dedup>,
+    spool: Spool,
 }
 
 impl User {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         name: String,
         webdav: Option<WebdavClient>,
         paperless: Option<PaperlessClient>,
         telegram: Option<TelegramClient>,
+        local: Option<LocalStorage>,
+        smtp: Option<SmtpClient>,
+        dedup: Option<Dedup>,
+        spool: Spool,
     ) -> Self {
         Self {
             name: name.to_lowercase(),
             webdav,
             paperless,
             telegram,
+            local,
+            smtp,
+            dedup,
+            spool,
         }
     }
 
-    pub fn from_env(name: String) -> Self {
+    pub fn from_env(name: String, spool: Spool) -> Self {
         let u = name.to_uppercase();
 
         let webdav = env::var(format!("{u}_WEBDAV_URL")).ok().map(|endpoint| {
@@ -76,8 +101,48 @@ impl User {
             let custom_fields = serde_json::from_str(custom_fields_raw)
                 .expect("Invalid value for Paperless custom fields");
 
-            PaperlessClient::new(endpoint, token, custom_fields)
-                .expect("Failed to construct Paperless client")
+            let title_template =
+                env::var(format!("{u}_PAPERLESS_TITLE")).unwrap_or("{id}".into());
+
+            let correspondent = env::var(format!("{u}_PAPERLESS_CORRESPONDENT"))
+                .ok()
+                .map(|value| value.parse().expect("Invalid Paperless correspondent ID"));
+
+            let document_type = env::var(format!("{u}_PAPERLESS_DOCUMENT_TYPE"))
+                .ok()
+                .map(|value| value.parse().expect("Invalid Paperless document type ID"));
+
+            let tags = env::var(format!("{u}_PAPERLESS_TAGS"))
+                .ok()
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(|tag| tag.parse().expect("Invalid Paperless tag ID"))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let asn_start = env::var(format!("{u}_PAPERLESS_ASN_START"))
+                .ok()
+                .map(|value| value.parse().expect("Invalid Paperless ASN start value"));
+
+            let asn_path = env::var(format!("{u}_PAPERLESS_ASN_PATH"))
+                .unwrap_or_else(|_| format!("paperless-asn-{name}.sled"));
+
+            PaperlessClient::new(
+                endpoint,
+                token,
+                custom_fields,
+                title_template,
+                correspondent,
+                document_type,
+                tags,
+                asn_start,
+                asn_path,
+            )
+            .expect("Failed to construct Paperless client")
         });
 
         let telegram = env::var(format!("{u}_TELEGRAM_TOKEN")).ok().map(|token| {
@@ -86,7 +151,39 @@ impl User {
             TelegramClient::new(chat, token).expect("Failed to construct Telegram client")
         });
 
-        Self::new(name, webdav, paperless, telegram)
+        let local = env::var(format!("{u}_LOCAL_STORAGE_DIR"))
+            .ok()
+            .map(LocalStorage::new);
+
+        let smtp = env::var(format!("{u}_SMTP_HOST")).ok().map(|host| {
+            let port = env::var(format!("{u}_SMTP_PORT"))
+                .ok()
+                .and_then(|p| p.parse().ok())
+                .unwrap_or(587);
+
+            let encryption = env::var(format!("{u}_SMTP_ENCRYPTION"))
+                .ok()
+                .map(|value| value.parse().expect("Invalid SMTP encryption mode"))
+                .unwrap_or(Encryption::StartTls);
+
+            let smtp_user = env::var(format!("{u}_SMTP_USER")).expect("No SMTP user provided");
+            let smtp_pass = env::var(format!("{u}_SMTP_PASS")).expect("No SMTP password provided");
+            let from = env::var(format!("{u}_SMTP_FROM")).expect("No SMTP sender address provided");
+            let to = env::var(format!("{u}_SMTP_TO")).expect("No SMTP recipient address provided");
+            let subject = env::var(format!("{u}_SMTP_SUBJECT")).unwrap_or("Scan {date}".into());
+
+            SmtpClient::new(host, port, encryption, smtp_user, smtp_pass, from, to, subject)
+                .expect("Failed to construct SMTP client")
+        });
+
+        let dedup = Dedup::from_env(&name);
+
+        Self::new(name, webdav, paperless, telegram, local, smtp, dedup, spool)
+    }
+
+    /// Exposes the user's local storage, if configured, for the retrieval API.
+    pub(crate) fn local(&self) -> Option<&LocalStorage> {
+        self.local.as_ref()
     }
 
     pub async fn store(&self, bytes: Bytes) -> Result<StatusCode, Rejection> {
@@ -100,46 +197,89 @@ impl User {
             bytes.len()
         );
 
-        if let Some(webdav) = self.webdav.clone() {
-            debug!("{id}\tCalling WebDAV ...");
-            self.store_in_background(id.clone(), bytes.clone().into(), webdav);
+        if let Some(dedup) = &self.dedup {
+            if dedup.is_duplicate(&bytes, &id) {
+                debug!("{id}\tSuppressed as a duplicate submission");
+                return Ok(StatusCode::OK);
+            }
+        }
+
+        if self.webdav.is_some() {
+            debug!("{id}\tSpooling for WebDAV ...");
+            self.spool
+                .enqueue(&self.name, &id, BackendKind::Webdav, &bytes)
+                .await;
+        }
+
+        if self.paperless.is_some() {
+            debug!("{id}\tSpooling for Paperless ...");
+            self.spool
+                .enqueue(&self.name, &id, BackendKind::Paperless, &bytes)
+                .await;
+        }
+
+        if self.local.is_some() {
+            debug!("{id}\tSpooling for local storage ...");
+            self.spool
+                .enqueue(&self.name, &id, BackendKind::Local, &bytes)
+                .await;
         }
 
-        if let Some(paperless) = self.paperless.clone() {
-            debug!("{id}\tCalling Paperless ...");
-            self.store_in_background(id, bytes.into(), paperless);
+        if self.smtp.is_some() {
+            debug!("{id}\tSpooling for SMTP ...");
+            self.spool
+                .enqueue(&self.name, &id, BackendKind::Smtp, &bytes)
+                .await;
         }
 
         Ok(StatusCode::OK)
     }
 
-    fn store_in_background(
+    /// Delivers a single spooled job to the backend it names. Used by the
+    /// spool worker for every delivery attempt, the first one included.
+    pub(crate) async fn dispatch(
         &self,
-        id: String,
-        bytes: Body,
-        backend: impl StorageBackend + Send + Sync + 'static,
-    ) {
-        let telegram = self.telegram.clone();
+        backend: BackendKind,
+        id: &str,
+        body: Body,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match backend {
+            BackendKind::Webdav => {
+                let webdav = self.webdav.clone().ok_or("WebDAV backend not configured")?;
+                webdav.put(id, body).await
+            }
+            BackendKind::Paperless => {
+                let paperless = self
+                    .paperless
+                    .clone()
+                    .ok_or("Paperless backend not configured")?;
+                paperless.put(id, body).await
+            }
+            BackendKind::Local => {
+                let local = self.local.clone().ok_or("Local storage not configured")?;
+                local.put(id, body).await
+            }
+            BackendKind::Smtp => {
+                let smtp = self.smtp.clone().ok_or("SMTP backend not configured")?;
+                smtp.put(id, body).await
+            }
+        }
+    }
+
+    /// Notifies the user over Telegram that a spooled job was abandoned.
+    pub(crate) fn notify_failure(&self, id: &str, err: &(dyn std::error::Error)) {
+        let Some(telegram) = self.telegram.clone() else {
+            return;
+        };
+
+        let id = id.to_string();
+        let message = format!(
+            "<b>EpicPrinter processing failed</b>\nFile: <i>{id}</i>\n\n<blockquote><code>{err}</code></blockquote>"
+        );
 
         tokio::spawn(async move {
-            let result = backend.put(&id, bytes).await;
-
-            match result {
-                Ok(_) => debug!("{id}\tUpload finished"),
-                Err(err) => {
-                    error!("{id}\tUpload failed: {err:?}");
-
-                    if let Some(telegram) = telegram {
-                        if let Err(notify_error) = telegram
-                            .send(format!(
-                                "<b>EpicPrinter processing failed</b>\nFile: <i>{id}</i>\n\n<blockquote><code>{err}</code></blockquote>"
-                            ))
-                            .await
-                        {
-                            error!("{id}\tFailed to notify user of error: {notify_error:?}");
-                        }
-                    }
-                }
+            if let Err(notify_error) = telegram.send(message).await {
+                error!("{id}\tFailed to notify user of error: {notify_error:?}");
             }
         });
     }